@@ -0,0 +1,203 @@
+//! 配置文件和环境变量
+//!
+//! 除了命令行参数，`access_key`/`secret_key`/`region`/`bucket`/`domain`/
+//! `threads`/`part_size` 都可以通过 `~/.qiniu/config.toml`（路径可用 `--config`
+//! 覆盖）和环境变量提供，优先级为 CLI > 环境变量 > 配置文件。配置文件支持多个
+//! profile，用 `--profile <name>` 切换：
+//!
+//! ```toml
+//! bucket = "default-bucket"
+//!
+//! [profiles.work]
+//! access_key = "..."
+//! secret_key = "..."
+//! bucket = "work-bucket"
+//! region = "z1"
+//! ```
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct ProfileConfig {
+    pub access_key: Option<String>,
+    pub secret_key: Option<String>,
+    pub region: Option<String>,
+    pub bucket: Option<String>,
+    pub domain: Option<String>,
+    pub threads: Option<u8>,
+    pub part_size: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(flatten)]
+    default: ProfileConfig,
+    #[serde(default)]
+    profiles: HashMap<String, ProfileConfig>,
+}
+
+fn default_config_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".qiniu")
+        .join("config.toml")
+}
+
+/// 加载配置文件，`profile` 指定时其字段覆盖默认 profile 的同名字段
+pub fn load(config_path: Option<&Path>, profile: Option<&str>) -> ProfileConfig {
+    let path = config_path
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(default_config_path);
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return ProfileConfig::default();
+    };
+    let Ok(config) = toml::from_str::<ConfigFile>(&content) else {
+        return ProfileConfig::default();
+    };
+    let mut resolved = config.default;
+    if let Some(profile) = profile.and_then(|name| config.profiles.get(name)) {
+        resolved.access_key = profile.access_key.clone().or(resolved.access_key);
+        resolved.secret_key = profile.secret_key.clone().or(resolved.secret_key);
+        resolved.region = profile.region.clone().or(resolved.region);
+        resolved.bucket = profile.bucket.clone().or(resolved.bucket);
+        resolved.domain = profile.domain.clone().or(resolved.domain);
+        resolved.threads = profile.threads.or(resolved.threads);
+        resolved.part_size = profile.part_size.or(resolved.part_size);
+    }
+    resolved
+}
+
+/// 按 CLI > 环境变量 > 配置文件 的优先级解析出一个配置项
+pub fn resolve<T: std::str::FromStr>(
+    cli_value: Option<T>,
+    env_key: &str,
+    config_value: Option<T>,
+) -> Option<T> {
+    cli_value
+        .or_else(|| std::env::var(env_key).ok().and_then(|v| v.parse().ok()))
+        .or(config_value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // 每个用例用独立的环境变量名，避免并行跑的测试互相踩 std::env 这个全局状态
+    fn with_env<R>(key: &str, value: &str, f: impl FnOnce() -> R) -> R {
+        std::env::set_var(key, value);
+        let r = f();
+        std::env::remove_var(key);
+        r
+    }
+
+    #[test]
+    fn resolve_cli_value_wins_over_env_and_config() {
+        with_env("QINIU_TEST_RESOLVE_CLI_WINS", "7", || {
+            let v = resolve(Some(1u8), "QINIU_TEST_RESOLVE_CLI_WINS", Some(9u8));
+            assert_eq!(v, Some(1));
+        });
+    }
+
+    #[test]
+    fn resolve_env_wins_over_config_when_cli_absent() {
+        with_env("QINIU_TEST_RESOLVE_ENV_WINS", "7", || {
+            let v: Option<u8> = resolve(None, "QINIU_TEST_RESOLVE_ENV_WINS", Some(9));
+            assert_eq!(v, Some(7));
+        });
+    }
+
+    #[test]
+    fn resolve_falls_back_to_config_when_cli_and_env_absent() {
+        std::env::remove_var("QINIU_TEST_RESOLVE_CONFIG_FALLBACK");
+        let v: Option<u8> = resolve(None, "QINIU_TEST_RESOLVE_CONFIG_FALLBACK", Some(9));
+        assert_eq!(v, Some(9));
+    }
+
+    #[test]
+    fn resolve_none_when_all_absent() {
+        std::env::remove_var("QINIU_TEST_RESOLVE_ALL_ABSENT");
+        let v: Option<u8> = resolve(None, "QINIU_TEST_RESOLVE_ALL_ABSENT", None);
+        assert_eq!(v, None);
+    }
+
+    #[test]
+    fn resolve_ignores_unparseable_env_value_and_falls_back_to_config() {
+        with_env("QINIU_TEST_RESOLVE_BAD_ENV", "not-a-number", || {
+            let v: Option<u8> = resolve(None, "QINIU_TEST_RESOLVE_BAD_ENV", Some(9));
+            assert_eq!(v, Some(9));
+        });
+    }
+
+    fn write_temp_config(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "qiniu_config_test_{}_{name}.toml",
+            std::process::id()
+        ));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn load_without_profile_returns_default_section() {
+        let path = write_temp_config(
+            "no_profile",
+            r#"
+            bucket = "default-bucket"
+            region = "z0"
+            "#,
+        );
+        let cfg = load(Some(&path), None);
+        assert_eq!(cfg.bucket.as_deref(), Some("default-bucket"));
+        assert_eq!(cfg.region.as_deref(), Some("z0"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn load_with_profile_overrides_matching_fields_only() {
+        let path = write_temp_config(
+            "profile_override",
+            r#"
+            bucket = "default-bucket"
+            region = "z0"
+            threads = 5
+
+            [profiles.work]
+            bucket = "work-bucket"
+            region = "z1"
+            "#,
+        );
+        let cfg = load(Some(&path), Some("work"));
+        assert_eq!(cfg.bucket.as_deref(), Some("work-bucket"));
+        assert_eq!(cfg.region.as_deref(), Some("z1"));
+        // profile 没有设置 threads，应该落回 default 段的值
+        assert_eq!(cfg.threads, Some(5));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn load_with_unknown_profile_falls_back_to_default_section() {
+        let path = write_temp_config(
+            "unknown_profile",
+            r#"
+            bucket = "default-bucket"
+            "#,
+        );
+        let cfg = load(Some(&path), Some("does-not-exist"));
+        assert_eq!(cfg.bucket.as_deref(), Some("default-bucket"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn load_missing_file_returns_default_profile_config() {
+        let path = std::env::temp_dir().join(format!(
+            "qiniu_config_test_{}_missing.toml",
+            std::process::id()
+        ));
+        let cfg = load(Some(&path), None);
+        assert_eq!(cfg.bucket, None);
+        assert_eq!(cfg.access_key, None);
+    }
+}
@@ -0,0 +1,95 @@
+//! 本地计算七牛 ETag(v1)
+//!
+//! 上传完成后可以用它和服务端返回的 `hash` 做比对，避免"上传成功"但内容在
+//! 传输过程中被破坏却没有察觉。算法见 <https://developer.qiniu.com/kodo/1231/appendix#3>：
+//! 按 4MiB 分块，块数为 1 时结果是 `[0x16] ++ sha1(content)`；否则对每个分块分别
+//! 计算 sha1 并拼接，再对拼接结果计算一次 sha1，结果是 `[0x96] ++ sha1(blocks_sha1)`，
+//! 最后对这 21 字节做带 padding 的 URL-safe base64 编码。
+
+use anyhow::Result;
+use base64::{engine::general_purpose::URL_SAFE, Engine as _};
+use sha1::{Digest, Sha1};
+use std::path::Path;
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+const BLOCK_SIZE: usize = 4 * 1024 * 1024;
+
+/// 计算给定 reader 内容的七牛 ETag(v1)
+pub async fn compute_qiniu_etag<R: AsyncRead + Unpin>(mut reader: R) -> Result<String> {
+    let mut block_shas: Vec<Vec<u8>> = Vec::new();
+    let mut buf = vec![0u8; BLOCK_SIZE];
+    loop {
+        let mut filled = 0;
+        while filled < BLOCK_SIZE {
+            let n = reader.read(&mut buf[filled..]).await?;
+            if n == 0 {
+                break;
+            }
+            filled += n;
+        }
+        if filled == 0 && !block_shas.is_empty() {
+            break;
+        }
+        let mut hasher = Sha1::new();
+        hasher.update(&buf[..filled]);
+        block_shas.push(hasher.finalize().to_vec());
+        if filled < BLOCK_SIZE {
+            break;
+        }
+    }
+    let (prefix, sha) = if block_shas.len() <= 1 {
+        (0x16u8, block_shas.into_iter().next().unwrap_or_default())
+    } else {
+        let mut hasher = Sha1::new();
+        for block in &block_shas {
+            hasher.update(block);
+        }
+        (0x96u8, hasher.finalize().to_vec())
+    };
+    let mut bytes = Vec::with_capacity(21);
+    bytes.push(prefix);
+    bytes.extend(sha);
+    Ok(URL_SAFE.encode(bytes))
+}
+
+/// 计算本地文件的七牛 ETag(v1)
+pub async fn compute_qiniu_etag_for_path(path: &Path) -> Result<String> {
+    let file = File::open(path).await?;
+    compute_qiniu_etag(file).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[tokio::test]
+    async fn empty_content_is_a_single_block() {
+        let etag = compute_qiniu_etag(Cursor::new(Vec::<u8>::new()))
+            .await
+            .unwrap();
+        assert_eq!(etag, "Fto5o-5ea0sNMlW_75VgGJCv2AcJ");
+    }
+
+    #[tokio::test]
+    async fn content_under_block_size_is_a_single_block() {
+        let data = b"hello, qiniu etag test".to_vec();
+        let etag = compute_qiniu_etag(Cursor::new(data)).await.unwrap();
+        assert_eq!(etag, "FlXEeerCHahb_IZy0CwKQCvEXTnN");
+    }
+
+    #[tokio::test]
+    async fn content_exactly_one_block_stays_a_single_block() {
+        let data = vec![b'a'; BLOCK_SIZE];
+        let etag = compute_qiniu_etag(Cursor::new(data)).await.unwrap();
+        assert_eq!(etag, "FuwQ-vpd56Izwiom1JHzCIdrQa4_");
+    }
+
+    #[tokio::test]
+    async fn content_one_byte_over_block_size_splits_into_two_blocks() {
+        let data = vec![b'a'; BLOCK_SIZE + 1];
+        let etag = compute_qiniu_etag(Cursor::new(data)).await.unwrap();
+        assert_eq!(etag, "lieGn00gWdbfwEIHaUpzu4drHeun");
+    }
+}
@@ -1,5 +1,11 @@
 //! 七牛文件上传工具
 
+mod checkpoint;
+mod config;
+mod download;
+mod etag;
+mod retry;
+
 use clap::{CommandFactory, Parser};
 use clap_complete::{generate, Shell};
 use colored::Colorize;
@@ -11,11 +17,11 @@ use std::{
     str::FromStr,
     time,
 };
-use std::{path::PathBuf, process::exit};
-use tokio::{
-    fs::{self, File},
-    io::AsyncRead,
+use std::{
+    path::{Path, PathBuf},
+    process::exit,
 };
+use tokio::fs::{self, File};
 
 // 将列表分块
 fn split_into_chunks<T>(list: Vec<T>, chunk_count: usize) -> Vec<Vec<T>>
@@ -28,20 +34,6 @@ where
         .collect()
 }
 
-// 获取下载链接
-fn get_download_url(domain_name: Option<String>, object_name: &str) -> String {
-    match domain_name {
-        Some(domain_name) => {
-            if domain_name.starts_with("http") {
-                format!("{domain_name}/{object_name}")
-            } else {
-                format!("https://{domain_name}/{object_name}")
-            }
-        }
-        None => "".to_string(),
-    }
-}
-
 // 遍历目录
 fn walk_dir(dir: PathBuf) -> Vec<PathBuf> {
     let mut res = Vec::new();
@@ -61,33 +53,95 @@ fn walk_dir(dir: PathBuf) -> Vec<PathBuf> {
 }
 
 /// 上传文件到七牛，开启进度条
-pub async fn upload_to_qiniu<R: AsyncRead + Send + Sync + 'static + std::marker::Unpin>(
+///
+/// 如果 `resume` 为 true，会在上传前检查是否存在 `bucket`/`object_name`/
+/// `file_size`/`mtime` 匹配的标记文件：如果存在，说明上次运行这份文件很可能被
+/// 中断过，这里会打印提示，但由于 `qiniu_uploader` 不提供分片级别的续传入口，
+/// 仍然会从头重新上传整个文件；不存在则落盘一个新标记。标记只有在上传（以及
+/// 启用 `verify` 时的校验）都成功之后才会清理，校验失败时保留标记，因为这次
+/// 上传实际上并未成功（见 [`checkpoint`] 模块文档）。
+///
+/// 如果 `verify` 为 true，上传成功后会重新读取 `local_path` 本地计算七牛 ETag，
+/// 和服务端返回的 `hash` 做比对，不一致时返回错误而不是误报成功。
+///
+/// 遇到连接错误或 5xx/429 时会按 `retries`/`retry_backoff_ms` 做指数退避重试；
+/// 第一次请求走 `qiniu`（默认 up-host），一旦失败过一次，后续重试改走
+/// `qiniu_alt_host`（备用 up-host）并保持，见 [`retry::select_uploader`]。
+#[allow(clippy::too_many_arguments)]
+pub async fn upload_to_qiniu(
     qiniu: QiniuUploader,
-    reader: R,
+    qiniu_alt_host: QiniuUploader,
+    local_path: &Path,
+    bucket_name: &str,
     object_name: &str,
     file_size: usize,
+    mtime: i64,
     part_size: Option<usize>,
     threads: Option<u8>,
+    resume: bool,
+    verify: bool,
+    retries: u32,
+    retry_backoff_ms: u64,
 ) -> Result<(), anyhow::Error> {
-    #[cfg(feature = "progress-bar")]
-    qiniu
-        .part_upload_file(object_name, reader, file_size, part_size, threads, None)
-        .await?;
+    if resume {
+        if checkpoint::exists(bucket_name, object_name, file_size as u64, mtime) {
+            eprintln!(
+                "{}",
+                format!("⚠️  检测到 {object_name} 上次可能被中断，重新上传整个文件").yellow()
+            );
+        } else {
+            checkpoint::save(bucket_name, object_name, file_size as u64, mtime)?;
+        }
+    }
+
+    let mut attempt = 0;
+    let upload_result = loop {
+        let file = File::open(local_path).await?;
+        let client = retry::select_uploader(attempt, &qiniu, &qiniu_alt_host);
+        #[cfg(feature = "progress-bar")]
+        let result = client
+            .clone()
+            .part_upload_file(object_name, file, file_size, part_size, threads, None)
+            .await;
+        #[cfg(not(feature = "progress-bar"))]
+        let result = client
+            .clone()
+            .part_upload_file(object_name, file, file_size, part_size, threads)
+            .await;
+        match result {
+            Ok(r) => break r,
+            Err(e) if attempt < retries && retry::is_retryable(&e) => {
+                retry::log_retry(attempt + 1, retries, object_name, &e);
+                tokio::time::sleep(retry::backoff_duration(attempt, retry_backoff_ms)).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    };
+
+    if verify {
+        let local_etag = etag::compute_qiniu_etag_for_path(local_path).await?;
+        if local_etag != upload_result.hash {
+            anyhow::bail!(
+                "文件校验失败，本地 ETag({local_etag}) 与服务端返回的 hash({}) 不一致",
+                upload_result.hash
+            );
+        }
+    }
 
-    #[cfg(not(feature = "progress-bar"))]
-    qiniu
-        .part_upload_file(object_name, reader, file_size, part_size, threads)
-        .await?;
+    if resume {
+        checkpoint::clear(bucket_name, object_name, file_size as u64, mtime);
+    }
     Ok(())
 }
 
 #[derive(Parser)]
 #[clap(version, about, long_about=None)]
 pub struct Cli {
-    /// 七牛access key，或自动从环境变量 `QINIU_ACCESS_KEY` 获取
+    /// 七牛access key，优先级：命令行 > 环境变量 `QINIU_ACCESS_KEY` > 配置文件
     #[clap(short, long)]
     access_key: Option<String>,
-    /// 七牛secret key, 或自动从环境变量 `QINIU_SECRET_KEY` 获取
+    /// 七牛secret key，优先级：命令行 > 环境变量 `QINIU_SECRET_KEY` > 配置文件
     #[clap(short, long)]
     secret_key: Option<String>,
     /// 对象名称，如果未指定会从`file_path`参数解析，一般不建议设置
@@ -96,13 +150,15 @@ pub struct Cli {
     /// 文件绝对路径，支持目录
     #[clap(short, long)]
     file_path: Option<PathBuf>,
-    /// 七牛bucket名称
+    /// 七牛bucket名称，优先级：命令行 > 环境变量 `QINIU_BUCKET` > 配置文件
     #[clap(short, long)]
     bucket_name: Option<String>,
     /// 七牛bucket region，如z0，华东-浙江(默认)，详见 https://developer.qiniu.com/kodo/1671/region-endpoint-fq
+    /// 优先级：命令行 > 环境变量 `QINIU_REGION`/`QINIU_ZONE` > 配置文件
     #[clap(long)]
     region: Option<String>,
     /// 下载域名，需要和bucket匹配，如果设置，会显示下载链接及输出二维码
+    /// 优先级：命令行 > 环境变量 `QINIU_DOMAIN` > 配置文件
     #[clap(short, long)]
     domain_name: Option<String>,
     /// 生成shell补全脚本, 支持Bash, Zsh, Fish, PowerShell, Elvish
@@ -112,11 +168,44 @@ pub struct Cli {
     #[clap(long, action)]
     no_qrcode: bool,
     /// 分片上传的大小，单位bytes，1M-1GB之间，如果指定，优先级比threads参数高
+    /// 优先级：命令行 > 环境变量 `QINIU_UPLOAD_BLOCK_SIZE` > 配置文件
     #[arg(long)]
     part_size: Option<usize>,
     /// 分片上传线程，在未指定part_size参数的情况下生效，默认5
+    /// 优先级：命令行 > 环境变量 `QINIU_UPLOAD_MAX_THREADS` > 配置文件
     #[arg(long)]
     threads: Option<u8>,
+    /// 记录上传状态以便检测异常中断，默认开启；注意由于 qiniu_uploader 不提供
+    /// 分片级别的续传能力，命中遗留记录时仍会重新上传整个文件，仅用于提示
+    #[clap(long, action, default_value_t = true)]
+    resume: bool,
+    /// 关闭上传状态记录
+    #[clap(long, action)]
+    no_resume: bool,
+    /// 上传完成后本地计算七牛 ETag 并与服务端返回的 hash 比对，校验失败视为上传失败
+    #[clap(long, action)]
+    verify: bool,
+    /// 私有空间，下载链接会额外签名，需要 access_key/secret_key 有下载权限
+    #[clap(long, action)]
+    private: bool,
+    /// 私有空间下载链接的有效期，单位秒，默认3600，配合 `--private` 使用
+    #[arg(long)]
+    expires: Option<u64>,
+    /// 仅上传文件夹，上传前对比远端 stat 的 size/hash，未变化的文件跳过上传
+    #[clap(long, action)]
+    sync: bool,
+    /// 配置文件路径，默认 `~/.qiniu/config.toml`
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// 使用配置文件中的指定 profile
+    #[arg(long)]
+    profile: Option<String>,
+    /// 遇到连接错误或 5xx/429 时的最大重试次数，默认3
+    #[arg(long)]
+    retries: Option<u32>,
+    /// 重试的指数退避基准时长，单位毫秒，默认200
+    #[arg(long)]
+    retry_backoff_ms: Option<u64>,
 }
 
 #[tokio::main]
@@ -135,42 +224,71 @@ async fn main() -> Result<(), anyhow::Error> {
         };
         return Ok(());
     }
-    let qiniu_access_key = match cli.access_key {
-        Some(key) => key,
-        None => match std::env::var("QINIU_ACCESS_KEY") {
-            Ok(key) => key,
-            Err(_) => {
-                eprintln!("{}", "Qiniu access_key 为空！".red());
-                exit(1)
-            }
-        },
-    };
-    let qiniu_secret_key = match cli.secret_key {
-        Some(key) => key,
-        None => match std::env::var("QINIU_SECRET_KEY") {
-            Ok(key) => key,
-            Err(_) => {
-                eprintln!("{}", "Qiniu secret_key 为空！".red());
-                exit(1)
-            }
-        },
-    };
+    let file_config = config::load(cli.config.as_deref(), cli.profile.as_deref());
+    let qiniu_access_key = config::resolve(
+        cli.access_key,
+        "QINIU_ACCESS_KEY",
+        file_config.access_key.clone(),
+    )
+    .unwrap_or_else(|| {
+        eprintln!("{}", "Qiniu access_key 为空！".red());
+        exit(1)
+    });
+    let qiniu_secret_key = config::resolve(
+        cli.secret_key,
+        "QINIU_SECRET_KEY",
+        file_config.secret_key.clone(),
+    )
+    .unwrap_or_else(|| {
+        eprintln!("{}", "Qiniu secret_key 为空！".red());
+        exit(1)
+    });
     let file_path = cli.file_path.unwrap_or_else(|| {
         eprintln!("{}", "file-path is required !".red());
         exit(1);
     });
-    let bucket_name = cli.bucket_name.unwrap_or_else(|| {
-        eprintln!("{}", "bucket-name is required !".red());
-        exit(1);
-    });
-    let region = QiniuRegionEnum::from_str(&cli.region.unwrap_or("z0".to_string())).unwrap();
+    let bucket_name = config::resolve(cli.bucket_name, "QINIU_BUCKET", file_config.bucket.clone())
+        .unwrap_or_else(|| {
+            eprintln!("{}", "bucket-name is required !".red());
+            exit(1);
+        });
+    let region_str = cli
+        .region
+        .or_else(|| std::env::var("QINIU_REGION").ok())
+        .or_else(|| std::env::var("QINIU_ZONE").ok())
+        .or(file_config.region.clone())
+        .unwrap_or_else(|| "z0".to_string());
+    let region = QiniuRegionEnum::from_str(&region_str).unwrap();
+    let region_alt = QiniuRegionEnum::from_str(&region_str).unwrap();
+    let domain_name = config::resolve(cli.domain_name, "QINIU_DOMAIN", file_config.domain.clone());
+    let threads = config::resolve(cli.threads, "QINIU_UPLOAD_MAX_THREADS", file_config.threads);
+    let part_size = config::resolve(
+        cli.part_size,
+        "QINIU_UPLOAD_BLOCK_SIZE",
+        file_config.part_size,
+    );
+    let resume = cli.resume && !cli.no_resume;
+    let verify = cli.verify;
+    let private = cli.private;
+    let expires = cli.expires.unwrap_or(3600);
+    let sync = cli.sync;
+    let retries = cli.retries.unwrap_or(3);
+    let retry_backoff_ms = cli.retry_backoff_ms.unwrap_or(200);
     let qiniu = QiniuUploader::new(
         qiniu_access_key.clone(),
         qiniu_secret_key.clone(),
-        bucket_name,
+        bucket_name.clone(),
         Some(region),
         false,
     );
+    // 备用 up-host：出现连接错误/5xx 后，从第一次重试起就切到这个客户端，见 retry 模块文档
+    let qiniu_alt_host = QiniuUploader::new(
+        qiniu_access_key.clone(),
+        qiniu_secret_key.clone(),
+        bucket_name.clone(),
+        Some(region_alt),
+        true,
+    );
     // 上传目录
     if file_path.is_dir() {
         let item_path = walk_dir(file_path.clone());
@@ -195,12 +313,16 @@ async fn main() -> Result<(), anyhow::Error> {
             let file_name = file_name.clone();
             let key_name = key_name.clone();
             let qiniu = qiniu.clone();
+            let qiniu_alt_host = qiniu_alt_host.clone();
             let dir_name = dir_name.clone();
-            let part_size = cli.part_size;
-            let domain_name = cli.domain_name.clone();
+            let domain_name = domain_name.clone();
+            let bucket_name = bucket_name.clone();
+            let qiniu_access_key = qiniu_access_key.clone();
+            let qiniu_secret_key = qiniu_secret_key.clone();
             let handle = tokio::spawn(async move {
                 let mut success = 0;
                 let mut fail = 0;
+                let mut skipped = 0;
                 for item in item_paths {
                     let mut object_name = item.to_str().unwrap().to_string();
                     if let Some(ref dest_dir) = key_name {
@@ -217,43 +339,107 @@ async fn main() -> Result<(), anyhow::Error> {
                             .to_lowercase();
                     }
                     let file = fs::File::open(item.clone()).await.unwrap();
-                    let file_size = file.metadata().await.unwrap().size();
+                    let metadata = file.metadata().await.unwrap();
+                    let file_size = metadata.size();
+                    let mtime = metadata.mtime();
+                    if sync {
+                        if let Ok(stat) = qiniu.clone().stat(&object_name).await {
+                            if stat.fsize == file_size {
+                                if let Ok(local_etag) =
+                                    etag::compute_qiniu_etag_for_path(&item).await
+                                {
+                                    if local_etag == stat.hash {
+                                        skipped += 1;
+                                        println!(
+                                            "⏭️  {} -> {} 跳过(未变化)",
+                                            item.to_str().unwrap().green(),
+                                            object_name.yellow(),
+                                        );
+                                        continue;
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    if resume {
+                        if checkpoint::exists(&bucket_name, &object_name, file_size, mtime) {
+                            eprintln!(
+                                "{}",
+                                format!(
+                                    "⚠️  检测到 {object_name} 上次可能被中断，重新上传整个文件"
+                                )
+                                .yellow()
+                            );
+                        } else {
+                            let _ = checkpoint::save(&bucket_name, &object_name, file_size, mtime);
+                        }
+                    }
                     let mut error_message = String::new();
-                    #[cfg(feature = "progress-bar")]
-                    match qiniu
-                        .clone()
-                        .part_upload_file_no_progress_bar(
-                            &object_name,
-                            file,
-                            file_size as usize,
-                            part_size,
-                            Some(1),
-                        )
-                        .await
-                    {
-                        Ok(_) => success += 1,
-                        Err(e) => {
-                            fail += 1;
-                            error_message = e.to_string();
+                    let mut remote_hash = String::new();
+                    let mut attempt = 0;
+                    loop {
+                        let file = fs::File::open(item.clone()).await.unwrap();
+                        let client = retry::select_uploader(attempt, &qiniu, &qiniu_alt_host);
+                        #[cfg(feature = "progress-bar")]
+                        let result = client
+                            .clone()
+                            .part_upload_file_no_progress_bar(
+                                &object_name,
+                                file,
+                                file_size as usize,
+                                part_size,
+                                Some(1),
+                            )
+                            .await;
+                        #[cfg(not(feature = "progress-bar"))]
+                        let result = client
+                            .clone()
+                            .part_upload_file(
+                                &object_name,
+                                file,
+                                file_size as usize,
+                                part_size,
+                                Some(1),
+                            )
+                            .await;
+                        match result {
+                            Ok(result) => {
+                                remote_hash = result.hash;
+                                break;
+                            }
+                            Err(e) if attempt < retries && retry::is_retryable(&e) => {
+                                retry::log_retry(attempt + 1, retries, &object_name, &e);
+                                tokio::time::sleep(retry::backoff_duration(
+                                    attempt,
+                                    retry_backoff_ms,
+                                ))
+                                .await;
+                                attempt += 1;
+                            }
+                            Err(e) => {
+                                error_message = e.to_string();
+                                break;
+                            }
+                        }
+                    }
+                    if error_message.is_empty() && verify {
+                        match etag::compute_qiniu_etag_for_path(&item).await {
+                            Ok(local_etag) if local_etag == remote_hash => {}
+                            Ok(local_etag) => {
+                                error_message = format!(
+                                    "文件校验失败，本地 ETag({local_etag}) 与服务端返回的 hash({remote_hash}) 不一致"
+                                );
+                            }
+                            Err(e) => error_message = e.to_string(),
                         }
                     }
-                    #[cfg(not(feature = "progress-bar"))]
-                    match qiniu
-                        .clone()
-                        .part_upload_file(
-                            &object_name,
-                            file,
-                            file_size as usize,
-                            part_size,
-                            Some(1),
-                        )
-                        .await
-                    {
-                        Ok(_) => success += 1,
-                        Err(e) => {
-                            fail += 1;
-                            error_message = e.to_string();
+                    if error_message.is_empty() {
+                        success += 1;
+                        if resume {
+                            checkpoint::clear(&bucket_name, &object_name, file_size, mtime);
                         }
+                    } else {
+                        fail += 1;
                     }
                     if !error_message.is_empty() {
                         eprintln!(
@@ -268,28 +454,40 @@ async fn main() -> Result<(), anyhow::Error> {
                             item.to_str().unwrap().green(),
                             object_name.yellow(),
                         );
-                        let download_url = get_download_url(domain_name.clone(), &object_name);
+                        let mut download_url =
+                            download::get_download_url(domain_name.clone(), &object_name);
                         if !download_url.is_empty() {
+                            if private {
+                                download_url = download::sign_private_url(
+                                    &download_url,
+                                    &qiniu_access_key,
+                                    &qiniu_secret_key,
+                                    expires,
+                                );
+                            }
                             println!("🔗 {}\n", download_url.yellow());
                         }
                     }
                 }
-                (success, fail)
+                (success, fail, skipped)
             });
             handles.push(handle);
         }
         let mut success = 0;
         let mut fail = 0;
+        let mut skipped = 0;
         for handle in handles {
             let res = handle.await.unwrap();
             success += res.0;
             fail += res.1;
+            skipped += res.2;
         }
         println!(
-            "🚀 文件夹 {} 上传完成\n🔥 {} 个文件上传成功, {} 个文件上传失败, {:.2}s elapsed.",
+            "🚀 文件夹 {} 上传完成\n🔥 {} 个文件上传成功, {} 个文件上传失败, {} 个文件跳过(未变化), {:.2}s elapsed.",
             file_path.to_str().unwrap().green(),
             success.to_string().green(),
             fail.to_string().red(),
+            skipped.to_string().cyan(),
             start.elapsed().as_secs_f64(),
         );
         return Ok(());
@@ -311,14 +509,23 @@ async fn main() -> Result<(), anyhow::Error> {
         }
     };
     // size in bytes
-    let size = file.metadata().await.unwrap().size();
+    let metadata = file.metadata().await.unwrap();
+    let size = metadata.size();
+    let mtime = metadata.mtime();
     match upload_to_qiniu(
         qiniu,
-        file,
+        qiniu_alt_host,
+        &file_path,
+        &bucket_name,
         object_name.as_str(),
         size as usize,
-        cli.part_size,
-        cli.threads,
+        mtime,
+        part_size,
+        threads,
+        resume,
+        verify,
+        retries,
+        retry_backoff_ms,
     )
     .await
     {
@@ -338,8 +545,16 @@ async fn main() -> Result<(), anyhow::Error> {
             );
         }
     };
-    let download_url = get_download_url(cli.domain_name, &object_name);
+    let mut download_url = download::get_download_url(domain_name, &object_name);
     if !download_url.is_empty() {
+        if private {
+            download_url = download::sign_private_url(
+                &download_url,
+                &qiniu_access_key,
+                &qiniu_secret_key,
+                expires,
+            );
+        }
         println!("🔗 {}", download_url.yellow());
         if !cli.no_qrcode {
             let code = QrCode::new(download_url).unwrap();
@@ -0,0 +1,108 @@
+//! 上传重试策略
+//!
+//! 瞬时的网络错误/5xx 不应该让一次分片上传直接判失败：这里对可重试的错误做指数
+//! 退避（带抖动）重试，每次重试都重新打开本地文件。
+//!
+//! `qiniu_uploader::QiniuUploader::new` 的最后一个 bool 参数控制走默认 up-host
+//! 还是 CDN 加速 up-host，这是目前唯一暴露出来的 host 选择入口（没有单独的 API
+//! 可以枚举或覆盖 up-host 列表）。`main.rs` 为同一个 region 各构造一个默认/备用
+//! host 的 `QiniuUploader`：第一次请求总是走默认 host；一旦失败过一次，后续的
+//! 重试改用备用 host 并保持（不再切回默认 host），避免在两个 host 之间来回抖动。
+
+use colored::Colorize;
+use rand::Rng;
+use std::time::Duration;
+
+/// 根据重试次数选择本次请求应该使用的 up-host：第一次请求（`attempt == 0`）用
+/// `primary`，之后的重试一律切到 `alternate`
+pub fn select_uploader<'a, T>(attempt: u32, primary: &'a T, alternate: &'a T) -> &'a T {
+    if attempt == 0 {
+        primary
+    } else {
+        alternate
+    }
+}
+
+/// 根据错误信息判断是否是值得重试的瞬时错误：连接类错误和 5xx/429
+pub fn is_retryable(err: &anyhow::Error) -> bool {
+    let msg = err.to_string().to_lowercase();
+    msg.contains("timeout")
+        || msg.contains("timed out")
+        || msg.contains("connect")
+        || msg.contains("reset")
+        || msg.contains("broken pipe")
+        || ["500", "502", "503", "504", "429"]
+            .iter()
+            .any(|code| msg.contains(code))
+}
+
+/// 指数退避加抖动：第 `attempt` 次重试（从0开始）前需要等待的时长
+pub fn backoff_duration(attempt: u32, base_ms: u64) -> Duration {
+    let exp_ms = base_ms.saturating_mul(1u64 << attempt.min(10));
+    let jitter_ms = rand::thread_rng().gen_range(0..=exp_ms / 2 + 1);
+    Duration::from_millis(exp_ms + jitter_ms)
+}
+
+/// 打印一条重试日志
+pub fn log_retry(attempt: u32, max_retries: u32, object_name: &str, err: &anyhow::Error) {
+    eprintln!(
+        "{}",
+        format!("⚠️  {object_name} 上传失败({attempt}/{max_retries})，{err}，即将重试").yellow()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retryable_on_connection_and_timeout_errors() {
+        assert!(is_retryable(&anyhow::anyhow!("Connection reset by peer")));
+        assert!(is_retryable(&anyhow::anyhow!("operation timed out")));
+        assert!(is_retryable(&anyhow::anyhow!("broken pipe")));
+    }
+
+    #[test]
+    fn retryable_on_5xx_and_429_status_codes() {
+        assert!(is_retryable(&anyhow::anyhow!(
+            "unexpected status code: 503"
+        )));
+        assert!(is_retryable(&anyhow::anyhow!("429 Too Many Requests")));
+    }
+
+    #[test]
+    fn not_retryable_on_other_errors() {
+        assert!(!is_retryable(&anyhow::anyhow!(
+            "invalid access_key/secret_key"
+        )));
+        assert!(!is_retryable(&anyhow::anyhow!("404 not found")));
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_with_base() {
+        // 抖动取值在 0..=exp_ms/2+1 之间、不会为负，所以下限就是未加抖动的指数退避时长
+        assert!(backoff_duration(0, 200).as_millis() >= 200);
+        assert!(backoff_duration(1, 200).as_millis() >= 400);
+        assert!(backoff_duration(2, 200).as_millis() >= 800);
+    }
+
+    #[test]
+    fn backoff_attempt_is_capped_to_avoid_overflow() {
+        // attempt 远大于10时左移被钳制在 1<<10，不应该 panic 或溢出
+        let d = backoff_duration(1000, 200);
+        assert!(d.as_millis() >= 200 * (1 << 10));
+    }
+
+    #[test]
+    fn select_uploader_uses_primary_on_first_attempt() {
+        let (primary, alternate) = ("primary", "alternate");
+        assert_eq!(*select_uploader(0, &primary, &alternate), "primary");
+    }
+
+    #[test]
+    fn select_uploader_switches_to_alternate_after_first_failure_and_stays() {
+        let (primary, alternate) = ("primary", "alternate");
+        assert_eq!(*select_uploader(1, &primary, &alternate), "alternate");
+        assert_eq!(*select_uploader(2, &primary, &alternate), "alternate");
+    }
+}